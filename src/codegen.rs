@@ -64,6 +64,15 @@ lazy_static! {
         table.insert("JMP", 0b111);
         table
     };
+
+    static ref COMP_REVERSE: HashMap<u16, &'static str> =
+        COMP_TABLE.iter().map(|(&comp, &bits)| (bits, comp)).collect();
+
+    static ref DEST_REVERSE: HashMap<u16, &'static str> =
+        DEST_TABLE.iter().map(|(&dest, &bits)| (bits, dest)).collect();
+
+    static ref JUMP_REVERSE: HashMap<u16, &'static str> =
+        JUMP_TABLE.iter().map(|(&jump, &bits)| (bits, jump)).collect();
 }
 
 
@@ -101,6 +110,18 @@ impl<'a> Display for CompileError<'a> {
     }
 }
 
+impl<'a> CompileError<'a> {
+    /// The source token the error is about, so callers can point a
+    /// diagnostic at it instead of just the start of the line.
+    pub fn token(&self) -> &'a str {
+        match *self {
+            LookupMiss(Comp(token)) => token,
+            LookupMiss(Dest(token)) => token,
+            LookupMiss(Jump(token)) => token,
+        }
+    }
+}
+
 impl<'a> Error for CompileError<'a> {
     fn description(&self) -> &str {
         "compilation error"
@@ -111,6 +132,31 @@ impl<'a> Error for CompileError<'a> {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnknownComp(u16),
+}
+
+use self::DecodeError::*;
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnknownComp(bits) => write!(f, "no computation matches bit pattern {:07b}", bits),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        "decode error"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        None
+    }
+}
+
 pub fn compile(inst: Inst) -> Result<u16, CompileError> {
     match inst {
         AInst { address } => Ok(address as u16 & 0x7FFFu16),
@@ -129,6 +175,26 @@ pub fn compile(inst: Inst) -> Result<u16, CompileError> {
     }
 }
 
+pub fn decompile(word: u16) -> Result<Inst<'static>, DecodeError> {
+    if word & 0x8000 == 0 {
+        Ok(AInst {
+            address: word & 0x7FFFu16,
+        })
+    } else {
+        let comp_bits = (word >> 6) & 0x7F;
+        let dest_bits = (word >> 3) & 0x7;
+        let jump_bits = word & 0x7;
+        let comp = *COMP_REVERSE.get(&comp_bits).ok_or(UnknownComp(comp_bits))?;
+        let dest = DEST_REVERSE.get(&dest_bits).cloned();
+        let jump = JUMP_REVERSE.get(&jump_bits).cloned();
+        Ok(CInst {
+            comp: comp,
+            dest: dest,
+            jump: jump,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,6 +225,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decompile_a_inst() {
+        assert_eq!(Ok(AInst { address: 42 }), decompile(42));
+        assert_eq!(Ok(AInst { address: 1 }), decompile(1));
+    }
+
+    #[test]
+    fn decompile_c_inst() {
+        assert_eq!(
+            Ok(CInst {
+                comp: "D|M",
+                dest: None,
+                jump: None,
+            }),
+            decompile(0b1111010101000000)
+        );
+        assert_eq!(
+            Ok(CInst {
+                comp: "D|A",
+                dest: Some("AM"),
+                jump: Some("JGE"),
+            }),
+            decompile(0b1110010101101011)
+        );
+    }
+
+    #[test]
+    fn decompile_unknown_comp() {
+        assert_eq!(Err(UnknownComp(0b1111111)), decompile(0xFFFF));
+    }
+
     #[test]
     fn compile_errors() {
         assert_eq!(
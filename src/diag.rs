@@ -0,0 +1,79 @@
+use parser::Line;
+
+/// A 1-based location in the original source file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+struct Diagnostic {
+    position: Position,
+    message: String,
+}
+
+/// Accumulates errors across a whole assembly run so that assembling a large
+/// program reports every problem at once instead of stopping at the first one.
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { entries: Vec::new() }
+    }
+
+    pub fn push(&mut self, position: Position, message: String) {
+        self.entries.push(Diagnostic {
+            position: position,
+            message: message,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Prints every accumulated error as `file:line:col: message`, followed
+    /// by the offending source line and a caret under the offending token.
+    pub fn report(&self, file: &str, lines: &[Line]) {
+        for diagnostic in &self.entries {
+            eprintln!(
+                "{}:{}:{}: {}",
+                file, diagnostic.position.line, diagnostic.position.col, diagnostic.message
+            );
+            if let Some(source) = lines.iter().find(|line| line.num == diagnostic.position.line) {
+                eprintln!("{}", source.raw);
+                eprintln!("{}^", " ".repeat(diagnostic.position.col.saturating_sub(1)));
+            }
+        }
+        eprintln!(
+            "{} error{}",
+            self.len(),
+            if self.len() == 1 { "" } else { "s" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_empty() {
+        assert!(Diagnostics::new().is_empty());
+    }
+
+    #[test]
+    fn accumulates_pushed_errors() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Position { line: 1, col: 1 }, String::from("a"));
+        diagnostics.push(Position { line: 2, col: 3 }, String::from("b"));
+        assert!(!diagnostics.is_empty());
+        assert_eq!(2, diagnostics.len());
+    }
+}
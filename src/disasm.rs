@@ -0,0 +1,68 @@
+use codegen::{decompile, DecodeError};
+use inst::Inst::*;
+use symtab::SymbolTable;
+
+/// Renders a single 16-bit machine word back into Hack assembly text,
+/// resolving predefined symbols (`SP`, `R5..R15`, `SCREEN`, `KBD`, ...) from
+/// `table` where possible; where several names share an address, the choice
+/// is made by `SymbolTable::resolve_name`, so e.g. address 0 comes back as
+/// `@SP`, not `@R0`. Labels cannot be recovered, so any other
+/// A-instruction address is emitted numerically.
+pub fn disassemble(word: u16, table: &SymbolTable) -> Result<String, DecodeError> {
+    let inst = decompile(word)?;
+    Ok(match inst {
+        AInst { address } => match table.resolve_name(address) {
+            Some(name) => format!("@{}", name),
+            None => format!("@{}", address),
+        },
+        CInst { comp, dest, jump } => {
+            let mut text = String::new();
+            if let Some(dest) = dest {
+                text.push_str(dest);
+                text.push('=');
+            }
+            text.push_str(comp);
+            if let Some(jump) = jump {
+                text.push(';');
+                text.push_str(jump);
+            }
+            text
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassembles_a_inst_with_predefined_symbol() {
+        let table = SymbolTable::new();
+        assert_eq!(Ok(String::from("@SCREEN")), disassemble(16384, &table));
+    }
+
+    #[test]
+    fn disassembles_a_inst_without_symbol() {
+        let table = SymbolTable::new();
+        assert_eq!(Ok(String::from("@123")), disassemble(123, &table));
+    }
+
+    #[test]
+    fn disassembles_c_inst() {
+        let table = SymbolTable::new();
+        assert_eq!(
+            Ok(String::from("AM=D|A;JGE")),
+            disassemble(0b1110010101101011, &table)
+        );
+        assert_eq!(
+            Ok(String::from("D|M")),
+            disassemble(0b1111010101000000, &table)
+        );
+    }
+
+    #[test]
+    fn disassemble_propagates_decode_error() {
+        let table = SymbolTable::new();
+        assert_eq!(Err(DecodeError::UnknownComp(0b1111111)), disassemble(0xFFFF, &table));
+    }
+}
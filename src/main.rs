@@ -1,7 +1,9 @@
 extern crate clap;
 #[macro_use]
 extern crate lazy_static;
-extern crate regex;
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
 
 use clap::{Arg, App};
 use std::error::Error;
@@ -12,10 +14,14 @@ use std::io::{Read, Write};
 use std::process::exit;
 
 mod codegen;
+mod diag;
+mod disasm;
 mod inst;
 mod parser;
 mod symtab;
 
+use diag::{Diagnostics, Position};
+
 fn existing_file(path: String) -> Result<(), String> {
     let info = fs::metadata(path).map_err(|e| e.description().to_string())?;
     if info.is_file() {
@@ -36,17 +42,85 @@ fn read_input(input_option: Option<&str>) -> io::Result<String> {
     Ok(buffer)
 }
 
-fn write_output(output_option: Option<&str>, buffer: String) -> io::Result<()> {
+fn read_input_bytes(input_option: Option<&str>) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if let Some(path) = input_option {
+        let mut file = File::open(path)?;
+        file.read_to_end(&mut buffer)?;
+    } else {
+        io::stdin().read_to_end(&mut buffer)?;
+    }
+    Ok(buffer)
+}
+
+/// Reassembles a raw ROM image (as written by `--format bin`) into words.
+fn words_from_be_bytes(bytes: &[u8]) -> io::Result<Vec<u16>> {
+    if bytes.len() % 2 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "truncated ROM image (odd number of bytes)",
+        ));
+    }
+    Ok(bytes.chunks(2).map(|word| ((word[0] as u16) << 8) | word[1] as u16).collect())
+}
+
+fn write_output(output_option: Option<&str>, buffer: &[u8]) -> io::Result<()> {
     if let Some(path) = output_option {
         let mut file = File::create(path)?;
-        file.write_all(buffer.as_bytes())?;
+        file.write_all(buffer)?;
     } else {
-        print!("{}", buffer);
+        io::stdout().write_all(buffer)?;
         io::stdout().flush().ok();
     }
     Ok(())
 }
 
+/// How assembled instructions are rendered on output.
+enum OutputFormat {
+    /// Newline-joined `{:016b}` bit strings (the historical default).
+    Bits,
+    /// Newline-joined four-nibble big-endian hex words.
+    Hex,
+    /// A raw ROM image: each word as two big-endian bytes, back to back.
+    Bin,
+}
+
+impl OutputFormat {
+    fn from_arg(arg: Option<&str>) -> OutputFormat {
+        match arg {
+            Some("hex") => OutputFormat::Hex,
+            Some("bin") => OutputFormat::Bin,
+            _ => OutputFormat::Bits,
+        }
+    }
+}
+
+fn write_words(output_option: Option<&str>, words: &[u16], format: OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Bin => {
+            let mut sink: Box<Write> = match output_option {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            for word in words {
+                sink.write_all(&word.to_be_bytes())?;
+            }
+            sink.flush()
+        }
+        _ => {
+            let code = words
+                .iter()
+                .map(|word| match format {
+                    OutputFormat::Hex => format!("{:04X}", word),
+                    _ => format!("{:016b}", word),
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            write_output(output_option, code.as_bytes())
+        }
+    }
+}
+
 macro_rules! catch {
     ($x: expr, $msg: expr) => {{
         $x.unwrap_or_else(|error| {
@@ -76,32 +150,89 @@ fn main() {
                 .help("Sets the output file to use")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("disassemble")
+                .short("d")
+                .long("disassemble")
+                .help("Disassembles a .hack binary back into assembly instead of assembling"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Sets the encoding used for assembled output, or expected on --disassemble input")
+                .takes_value(true)
+                .possible_values(&["bits", "hex", "bin"]),
+        )
         .get_matches();
 
+    if matches.is_present("disassemble") {
+        let format = OutputFormat::from_arg(matches.value_of("format"));
+        let table = symtab::SymbolTable::new();
+        let words = match format {
+            OutputFormat::Bin => {
+                let bytes = catch!(read_input_bytes(matches.value_of("input")), "Input error");
+                catch!(words_from_be_bytes(&bytes), "Input error")
+            }
+            OutputFormat::Bits | OutputFormat::Hex => {
+                let radix = if let OutputFormat::Hex = format { 16 } else { 2 };
+                let buffer = catch!(read_input(matches.value_of("input")), "Input error");
+                buffer
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| catch!(u16::from_str_radix(line.trim(), radix), "Invalid input word"))
+                    .collect()
+            }
+        };
+        let code = words
+            .iter()
+            .map(|&word| catch!(disasm::disassemble(word, &table), "Decode error"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        catch!(
+            write_output(matches.value_of("output"), code.as_bytes()),
+            "Output error"
+        );
+        return;
+    }
+
     let buffer = catch!(read_input(matches.value_of("input")), "Input error");
     let mut table = symtab::SymbolTable::new();
     let lines = parser::preprocess(&buffer);
+    let mut diagnostics = Diagnostics::new();
+    parser::collect_labels(&lines, &mut table, &mut diagnostics);
+
+    let mut words = Vec::new();
+    for line in lines.iter().filter(|line| parser::label_name(&line.text).is_none()) {
+        match parser::parse_inst(&line.text, &mut table) {
+            Ok(inst) => match codegen::compile(inst) {
+                Ok(word) => words.push(word),
+                Err(error) => {
+                    let position = Position {
+                        line: line.num,
+                        col: line.col_of(error.token()),
+                    };
+                    diagnostics.push(position, format!("{}", error));
+                }
+            },
+            Err(error) => {
+                let col = error.token().map(|token| line.col_of(token)).unwrap_or_else(|| line.col());
+                let position = Position { line: line.num, col: col };
+                diagnostics.push(position, format!("{}", error));
+            }
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        let file = matches.value_of("input").unwrap_or("<stdin>");
+        diagnostics.report(file, &lines);
+        exit(1);
+    }
+
+    let format = OutputFormat::from_arg(matches.value_of("format"));
     catch!(
-        parser::collect_labels(&lines, &mut table),
-        "Unable to collect labels"
-    );
-    let insts = lines
-        .iter()
-        .filter(|line| parser::label_name(line).is_none())
-        .map(|line| {
-            catch!(parser::parse_inst(line, &mut table), "Parse error")
-        });
-    let code = insts
-        .map(|inst| {
-            format!(
-                "{:016b}",
-                catch!(codegen::compile(inst), "Compilation error")
-            )
-        })
-        .collect::<Vec<String>>()
-        .join("\n");
-    catch!(
-        write_output(matches.value_of("output"), code),
+        write_words(matches.value_of("output"), &words, format),
         "Output error"
     );
 }
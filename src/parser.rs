@@ -1,18 +1,22 @@
+use diag::{Diagnostics, Position};
 use inst::Inst;
 use inst::Inst::*;
-use regex::Regex;
-use std::convert::From;
+use pest::Parser;
 use std::error::Error;
 use std::fmt;
 use std::fmt::Display;
 use std::iter::Iterator;
-use std::num::ParseIntError;
 use symtab::{BindError, SymbolTable};
 
+/// PEG grammar for labels, A-instructions and C-instructions, see `hack.pest`.
+#[derive(Parser)]
+#[grammar = "hack.pest"]
+struct HackParser;
+
 #[derive(Debug, PartialEq)]
 pub enum ParseError<'a> {
-    InvalidAddress,
-    BindError(BindError<'a>),
+    InvalidAddress(&'a str),
+    BindFailure { error: BindError<'a>, token: &'a str },
     UnknownInst(&'a str),
 }
 
@@ -21,8 +25,8 @@ use self::ParseError::*;
 impl<'a> Display for ParseError<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            InvalidAddress => write!(f, "unable to parse address"),
-            BindError(ref error) => write!(f, "unable to bind symbol to address: {}", error),
+            InvalidAddress(token) => write!(f, "unable to parse address `{}'", token),
+            BindFailure { ref error, .. } => write!(f, "unable to bind symbol to address: {}", error),
             UnknownInst(line) => write!(f, "unknown instruction: {}", line),
         }
     }
@@ -38,57 +42,85 @@ impl<'a> Error for ParseError<'a> {
     }
 }
 
-impl<'a> From<ParseIntError> for ParseError<'a> {
-    fn from(_: ParseIntError) -> Self {
-        InvalidAddress
+impl<'a> ParseError<'a> {
+    /// The offending token, when the error can be pinned to one (a whole-line
+    /// failure like `UnknownInst` has no single token to point at).
+    pub fn token(&self) -> Option<&'a str> {
+        match *self {
+            InvalidAddress(token) => Some(token),
+            BindFailure { token, .. } => Some(token),
+            UnknownInst(_) => None,
+        }
     }
 }
 
-impl<'a> From<BindError<'a>> for ParseError<'a> {
-    fn from(error: BindError<'a>) -> Self {
-        BindError(error)
+/// A physical source line, kept alongside its original 1-based line number
+/// so later errors can be reported against the file the user actually wrote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub num: usize,
+    pub raw: String,
+    pub text: String,
+}
+
+impl Line {
+    /// 1-based column of the first non-whitespace character in `raw`.
+    pub fn col(&self) -> usize {
+        self.raw
+            .find(|c: char| !c.is_whitespace())
+            .map(|index| index + 1)
+            .unwrap_or(1)
+    }
+
+    /// 1-based column in `raw` of `token`, a substring of `self.text` as
+    /// handed back by a pest pair or a compiled `Inst` field. Valid because
+    /// `text` is always a trimmed, comment-stripped slice of `raw` sharing
+    /// its characters, so a byte offset into one maps onto the other.
+    pub fn col_of(&self, token: &str) -> usize {
+        let offset = token.as_ptr() as usize - self.text.as_ptr() as usize;
+        self.col() + offset
     }
 }
 
-type CleanLines = Vec<String>;
+type CleanLines = Vec<Line>;
 
 pub fn preprocess(text: &str) -> CleanLines {
     text.lines()
-        .map(|line| {
-            line.replace(|c: char| c.is_whitespace(), "")
-                .split("//")
-                .next()
-                .unwrap()
-                .trim()
-                .to_string()
+        .enumerate()
+        .map(|(index, raw)| {
+            let text = raw.split("//").next().unwrap().trim().to_string();
+            Line {
+                num: index + 1,
+                raw: raw.to_string(),
+                text: text,
+            }
         })
-        .filter(|line| !line.is_empty())
+        .filter(|line| !line.text.is_empty())
         .collect()
 }
 
-lazy_static! {
-    static ref LABEL: Regex = Regex::new(r"\(\s*(?P<label>\pL[\pL\d_\.\$]*)\s*\)").unwrap();
-    static ref A_INST: Regex = Regex::new(r"^@((?P<address>\d+)|(?P<symbol>\pL[\pL\d_\.\$]*))$").unwrap();
-    static ref C_INST: Regex = Regex::new(concat!(r"^((?P<dest>[AMD]{1,3})\s*=\s*)?",
-                                                  r"(?P<comp>[\-\+\|&!01ADM]+)",
-                                                  r"(\s*;\s*(?P<jump>[EGJLMNPQT]{3}))?$")).unwrap();
-}
-
 pub fn label_name(line: &str) -> Option<&str> {
-    if let Some(parts) = LABEL.captures(line) {
-        Some(parts.name("label").unwrap().as_str())
+    let top = HackParser::parse(Rule::line, line).ok()?.next()?;
+    let matched = top.into_inner().next()?;
+    if matched.as_rule() == Rule::label {
+        matched.into_inner().next().map(|symbol| symbol.as_str())
     } else {
         None
     }
 }
 
-pub fn collect_labels(lines: &CleanLines, table: &mut SymbolTable) {
+pub fn collect_labels(lines: &CleanLines, table: &mut SymbolTable, diagnostics: &mut Diagnostics) {
     let mut label_count = 0;
     for (row, line) in lines.iter().enumerate() {
         let address = row as u16 - label_count;
-        if let Some(label) = label_name(line) {
-            // TODO handle bind errors
-            table.bind(label, address).ok();
+        if let Some(label) = label_name(&line.text) {
+            if let Err(error) = table.bind(label, address) {
+                let position = Position {
+                    line: line.num,
+                    col: line.col_of(label),
+                };
+                diagnostics.push(position, format!("{}", error));
+            }
             label_count += 1;
         }
     }
@@ -98,21 +130,43 @@ pub fn parse_inst<'a, 'b>(
     line: &'a str,
     table: &'b mut SymbolTable,
 ) -> Result<Inst<'a>, ParseError<'a>> {
-    if let Some(parts) = A_INST.captures(line) {
-        let address = if let Some(symbol) = parts.name("symbol") {
-            table.resolve_or_bind(symbol.as_str())?
-        } else {
-            parts.name("address").unwrap().as_str().parse::<u16>()?
-        };
-        Ok(AInst { address: address })
-    } else if let Some(parts) = C_INST.captures(line) {
-        (Ok(CInst {
-            comp: parts.name("comp").unwrap().as_str(),
-            dest: parts.name("dest").map(|x| x.as_str()),
-            jump: parts.name("jump").map(|x| x.as_str()),
-        }))
-    } else {
-        Err(UnknownInst(line))
+    let top = HackParser::parse(Rule::instruction, line)
+        .map_err(|_| UnknownInst(line))?
+        .next()
+        .unwrap();
+    let pair = top.into_inner().next().unwrap();
+    match pair.as_rule() {
+        Rule::a_inst => {
+            let operand = pair.into_inner().next().unwrap();
+            let token = operand.as_str();
+            let address = match operand.as_rule() {
+                Rule::address => token.parse::<u16>().map_err(|_| InvalidAddress(token))?,
+                Rule::symbol => table.resolve_or_bind(token).map_err(|error| {
+                    BindFailure { error: error, token: token }
+                })?,
+                _ => unreachable!(),
+            };
+            Ok(AInst { address: address })
+        }
+        Rule::c_inst => {
+            let mut dest = None;
+            let mut comp = None;
+            let mut jump = None;
+            for part in pair.into_inner() {
+                match part.as_rule() {
+                    Rule::dest => dest = Some(part.as_str()),
+                    Rule::comp => comp = Some(part.as_str()),
+                    Rule::jump => jump = Some(part.as_str()),
+                    _ => unreachable!(),
+                }
+            }
+            Ok(CInst {
+                comp: comp.unwrap(),
+                dest: dest,
+                jump: jump,
+            })
+        }
+        _ => unreachable!(),
     }
 }
 
@@ -120,14 +174,24 @@ pub fn parse_inst<'a, 'b>(
 mod tests {
     use super::*;
 
+    fn texts(lines: &CleanLines) -> Vec<&str> {
+        lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
     #[test]
     fn whitespaces_trimmed() {
-        assert_eq!(vec!["a", "b", "cd"], preprocess(" a\t \n\t b\r\n c d "));
+        assert_eq!(vec!["a", "b", "c d"], texts(&preprocess(" a\t \n\t b\r\n c d ")));
     }
 
     #[test]
     fn comments_removed() {
-        assert_eq!(vec!["b"], preprocess("// x\n\t b // y\r\n // c d"))
+        assert_eq!(vec!["b"], texts(&preprocess("// x\n\t b // y\r\n // c d")))
+    }
+
+    #[test]
+    fn line_numbers_preserved() {
+        let lines = preprocess("a\n\nb\n// c\nd");
+        assert_eq!(vec![1, 3, 5], lines.iter().map(|line| line.num).collect::<Vec<_>>());
     }
 
     #[test]
@@ -139,17 +203,44 @@ mod tests {
     #[test]
     fn labels_collected() {
         let mut table = SymbolTable::new();
+        let mut diagnostics = Diagnostics::new();
         let lines = preprocess("(a)\nb\nc\n \n(d)\ne");
-        collect_labels(&lines, &mut table);
+        collect_labels(&lines, &mut table, &mut diagnostics);
         assert_eq!(Some(0), table.resolve("a"));
         assert_eq!(Some(2), table.resolve("d"));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn duplicate_label_reported() {
+        let mut table = SymbolTable::new();
+        let mut diagnostics = Diagnostics::new();
+        let lines = preprocess("(a)\nb\n(a)\nc");
+        collect_labels(&lines, &mut table, &mut diagnostics);
+        assert_eq!(Some(0), table.resolve("a"));
+        assert_eq!(1, diagnostics.len());
+    }
+
+    #[test]
+    fn line_col_points_at_first_token() {
+        let lines = preprocess("  @foo\nbar");
+        assert_eq!(3, lines[0].col());
+        assert_eq!(1, lines[1].col());
+    }
+
+    #[test]
+    fn col_of_locates_token_past_leading_whitespace() {
+        let lines = preprocess("   @99999");
+        let token = &lines[0].text[1..];
+        assert_eq!("99999", token);
+        assert_eq!(5, lines[0].col_of(token));
     }
 
     #[test]
     fn parse_a_inst() {
         let mut table = SymbolTable::new();
         assert_eq!(Ok(AInst { address: 42 }), parse_inst("@42", &mut table));
-        assert_eq!(Err(InvalidAddress), parse_inst("@70000", &mut table));
+        assert_eq!(Err(InvalidAddress("70000")), parse_inst("@70000", &mut table));
         table.bind("X", 42).ok();
         assert_eq!(Ok(AInst { address: 42 }), parse_inst("@X", &mut table));
         assert_eq!(Ok(AInst { address: 16 }), parse_inst("@Y", &mut table));
@@ -189,4 +280,10 @@ mod tests {
         let mut table = SymbolTable::new();
         assert_eq!(Err(UnknownInst(";=;=")), parse_inst(";=;=", &mut table));
     }
+
+    #[test]
+    fn parse_rejects_malformed_comp() {
+        let mut table = SymbolTable::new();
+        assert_eq!(Err(UnknownInst("D+D")), parse_inst("D+D", &mut table));
+    }
 }
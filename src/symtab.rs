@@ -4,10 +4,14 @@ use std::fmt;
 use std::fmt::Display;
 use std::error::Error;
 
+/// Last RAM address available for variables; 16384 and up is memory-mapped
+/// to the `SCREEN` and `KBD` I/O devices and cannot be used for storage.
+pub const RAM_LIMIT: u16 = 16383;
+
 #[derive(Debug, PartialEq)]
 pub enum BindError<'a> {
     AlreadyBound { symbol: &'a str },
-    TooManyBindings,
+    RamOverflow,
 }
 
 use self::BindError::*;
@@ -16,7 +20,7 @@ impl<'a> Display for BindError<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             AlreadyBound { symbol } => write!(f, "Unable to rebind symbol {}", symbol),
-            TooManyBindings => write!(f, "Too many bindings"),
+            RamOverflow => write!(f, "No RAM left for variables (limit is {})", RAM_LIMIT),
         }
     }
 }
@@ -31,51 +35,75 @@ impl<'a> Error for BindError<'a> {
     }
 }
 
+fn is_register_name(name: &str) -> bool {
+    name.starts_with('R') && name.len() > 1 && name[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// The Hack platform's predefined symbols: registers `R0..R15`, the virtual
+/// segment pointers, and the memory-mapped I/O devices.
+const PREDEFINED_SYMBOLS: [(&'static str, u16); 23] = [
+    ("R0", 0),
+    ("R1", 1),
+    ("R2", 2),
+    ("R3", 3),
+    ("R4", 4),
+    ("R5", 5),
+    ("R6", 6),
+    ("R7", 7),
+    ("R8", 8),
+    ("R9", 9),
+    ("R10", 10),
+    ("R11", 11),
+    ("R12", 12),
+    ("R13", 13),
+    ("R14", 14),
+    ("R15", 15),
+    ("SP", 0),
+    ("LCL", 1),
+    ("ARG", 2),
+    ("THIS", 3),
+    ("THAT", 4),
+    ("SCREEN", 16384),
+    ("KBD", 24576),
+];
+
+/// Configures which symbols a `SymbolTable` starts out with and where it
+/// begins allocating variables. `Default` reproduces the standard Hack
+/// predefined symbols with variables starting at address 16.
+pub struct SymbolTableConfig<'a> {
+    pub predefined: &'a [(&'a str, u16)],
+    pub variable_base: u16,
+}
+
+impl<'a> Default for SymbolTableConfig<'a> {
+    fn default() -> SymbolTableConfig<'a> {
+        SymbolTableConfig {
+            predefined: &PREDEFINED_SYMBOLS,
+            variable_base: 16,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SymbolTable {
     entries: HashMap<String, u16>,
     next_local: u16,
 }
 
-lazy_static! {
-    static ref INITIAL_TABLE: SymbolTable = {
-        let initial_entries: [(&str, u16); 23] = [
-            ("R0", 0),
-            ("R1", 1),
-            ("R2", 2),
-            ("R3", 3),
-            ("R4", 4),
-            ("R5", 5),
-            ("R6", 6),
-            ("R7", 7),
-            ("R8", 8),
-            ("R9", 9),
-            ("R10", 10),
-            ("R11", 11),
-            ("R12", 12),
-            ("R13", 13),
-            ("R14", 14),
-            ("R15", 15),
-            ("SP", 0),
-            ("LCL", 1),
-            ("ARG", 2),
-            ("THIS", 3),
-            ("THAT", 4),
-            ("SCREEN", 16384),
-            ("KBD", 24576),
-        ];
-
-        let mut table = SymbolTable { entries: HashMap::new(), next_local: 16 };
-        for entry in initial_entries.iter() {
-            table.bind(entry.0, entry.1).ok();
-        }
-        table
-    };
-}
-
 impl SymbolTable {
     pub fn new() -> SymbolTable {
-        INITIAL_TABLE.clone()
+        SymbolTable::with_config(&SymbolTableConfig::default())
+    }
+
+    pub fn with_config(config: &SymbolTableConfig) -> SymbolTable {
+        let mut table = SymbolTable {
+            entries: HashMap::new(),
+            next_local: config.variable_base,
+        };
+        for &(symbol, address) in config.predefined {
+            table.bind(symbol, address).ok();
+        }
+        table
     }
 
     pub fn bind<'a>(&mut self, symbol: &'a str, address: u16) -> Result<u16, BindError<'a>> {
@@ -95,10 +123,23 @@ impl SymbolTable {
         self.entries.get(symbol).map(|&x| x)
     }
 
+    /// Finds a symbol bound to `address`, preferring a mnemonic name (e.g. `SP`)
+    /// over an aliasing `R`-register name (e.g. `R0`) when both are bound.
+    pub fn resolve_name(&self, address: u16) -> Option<&str> {
+        let mut candidates = self.entries
+            .iter()
+            .filter(|&(_, &bound)| bound == address)
+            .map(|(name, _)| name.as_str());
+        candidates
+            .clone()
+            .find(|name| !is_register_name(name))
+            .or_else(|| candidates.next())
+    }
+
     pub fn resolve_or_bind<'a>(&mut self, symbol: &'a str) -> Result<u16, BindError<'a>> {
         self.resolve(symbol).map(Ok).unwrap_or_else(|| {
-            if self.next_local == <u16>::max_value() {
-                return Err(TooManyBindings);
+            if self.next_local > RAM_LIMIT {
+                return Err(RamOverflow);
             }
             let address = self.next_local;
             self.next_local += 1;
@@ -145,6 +186,15 @@ mod tests {
         assert_eq!(Err(AlreadyBound { symbol: "SP" }), table.bind("SP", 42));
     }
 
+    #[test]
+    fn resolve_name_prefers_mnemonic_over_register_alias() {
+        let table = SymbolTable::new();
+        assert_eq!(Some("SP"), table.resolve_name(0));
+        assert_eq!(Some("R5"), table.resolve_name(5));
+        assert_eq!(Some("SCREEN"), table.resolve_name(16384));
+        assert_eq!(None, table.resolve_name(100));
+    }
+
     #[test]
     fn resolve_or_bind() {
         let mut table = SymbolTable::new();
@@ -152,9 +202,21 @@ mod tests {
         assert_eq!(Ok(1), table.resolve_or_bind("A"));
         assert_eq!(Ok(16), table.resolve_or_bind("B"));
         assert_eq!(Ok(17), table.resolve_or_bind("C"));
-        for address in 18..<u16>::max_value() {
+        for address in 18..=RAM_LIMIT {
             table.resolve_or_bind(format!("X{}", address).as_str()).ok();
         }
-        assert_eq!(Err(TooManyBindings), table.resolve_or_bind("Z"));
+        assert_eq!(Err(RamOverflow), table.resolve_or_bind("Z"));
+    }
+
+    #[test]
+    fn with_config_uses_custom_predefined_symbols_and_base() {
+        let config = SymbolTableConfig {
+            predefined: &[("FOO", 5)],
+            variable_base: 100,
+        };
+        let mut table = SymbolTable::with_config(&config);
+        assert_eq!(Some(5), table.resolve("FOO"));
+        assert_eq!(None, table.resolve("SP"));
+        assert_eq!(Ok(100), table.resolve_or_bind("bar"));
     }
 }